@@ -1,217 +1,237 @@
-use bollard::Docker;
-use bollard::errors::Error;
-
-use bollard::query_parameters::{
-    CreateContainerOptions, InspectContainerOptions, ListContainersOptions, RemoveContainerOptions,
-    StartContainerOptions, StopContainerOptions,
-};
-
-use bollard::models::{ContainerCreateBody, ContainerSummaryStateEnum, HostConfig};
+mod reconcile;
+mod retry;
+mod service;
+mod spec;
+mod worker;
 
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tokio::time::sleep;
-use uuid::Uuid;
-
-const SERVICE_NAME: &str = "demo-nginx";
-const IMAGE: &str = "nginx:alpine";
-const DESIRED_REPLICAS: usize = 3;
 
-const MAX_CONSECUTIVE_FAILURES: u32 = 5;
-const BACKOFF_DURATION_SECS: u64 = 30;
-const FAILURE_RESET_AFTER_SECS: u64 = 300;
+use async_trait::async_trait;
+use bollard::Docker;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::Mutex;
 
-#[derive(Debug, Default)]
-struct BackoffState {
-    consecutive_failures: u32,
-    last_failure: Option<Instant>,
+use reconcile::{
+    force_remove_remaining, gc_orphaned_containers, health_check_service, list_managed_containers,
+    reconcile_all,
+};
+use retry::RetryQueue;
+use service::OrchestratorError;
+use spec::{load_spec, DEFAULT_SHUTDOWN_DEADLINE_SECS};
+use worker::{run_control_loop, Scheduler, Worker, WorkerState};
+
+const RECONCILE_INTERVAL_SECS: u64 = 5;
+const HEALTH_WATCH_INTERVAL_SECS: u64 = 5;
+const GC_INTERVAL_SECS: u64 = 30;
+
+// Each worker below re-reads the desired-state spec from `spec_path` at the
+// start of every cycle, so edits to the file (replica counts, image tags,
+// dependencies) take effect without restarting the orchestrator.
+
+struct ReconcilerWorker {
+    docker: Docker,
+    spec_path: PathBuf,
+    retry_queues: Arc<Mutex<HashMap<String, RetryQueue>>>,
 }
 
-impl BackoffState {
-    fn register_failure(&mut self) {
-        self.consecutive_failures += 1;
-        self.last_failure = Some(Instant::now());
+#[async_trait]
+impl Worker for ReconcilerWorker {
+    fn name(&self) -> &str {
+        "reconciler"
     }
 
-    fn maybe_reset(&mut self) {
-        if let Some(last) = self.last_failure
-            && last.elapsed() > Duration::from_secs(FAILURE_RESET_AFTER_SECS)
-        {
-            self.consecutive_failures = 0;
-            self.last_failure = None;
-        }
+    async fn step(&mut self) -> Result<WorkerState, OrchestratorError> {
+        let config = load_spec(&self.spec_path).await?;
+        let mut retry_queues = self.retry_queues.lock().await;
+        let changed = reconcile_all(&self.docker, &config.services, &mut retry_queues).await?;
+        Ok(if changed { WorkerState::Active } else { WorkerState::Idle })
     }
+}
 
-    fn in_backoff(&self) -> bool {
-        if let Some(last) = self.last_failure
-            && self.consecutive_failures >= MAX_CONSECUTIVE_FAILURES
-            && last.elapsed() < Duration::from_secs(BACKOFF_DURATION_SECS)
-        {
-            return true;
-        }
+struct HealthWatcherWorker {
+    docker: Docker,
+    spec_path: PathBuf,
+    retry_queues: Arc<Mutex<HashMap<String, RetryQueue>>>,
+    unhealthy_since: HashMap<String, HashMap<String, Instant>>,
+}
 
-        false
+#[async_trait]
+impl Worker for HealthWatcherWorker {
+    fn name(&self) -> &str {
+        "health-watcher"
     }
-}
 
-#[tokio::main]
-async fn main() -> Result<(), Error> {
-    let docker = connect_with_retry().await;
-    let mut backoff = BackoffState::default();
-    println!("Connected to docker, starting orchestrataaa");
-    println!("Service: {SERVICE_NAME}, image: {IMAGE}, desired replicas: {DESIRED_REPLICAS}");
+    async fn step(&mut self) -> Result<WorkerState, OrchestratorError> {
+        let config = load_spec(&self.spec_path).await?;
+        let mut retry_queues = self.retry_queues.lock().await;
+        let mut changed = false;
 
-    loop {
-        if let Err(e) = reconcile(&docker, &mut backoff).await {
-            eprintln!("reconcile error: {:?}", e);
+        for service in &config.services {
+            let retry = retry_queues
+                .entry(service.name.clone())
+                .or_insert_with(|| RetryQueue::new(service.backoff.clone()));
+            let unhealthy = self.unhealthy_since.entry(service.name.clone()).or_default();
+
+            if health_check_service(&self.docker, service, retry, unhealthy).await? {
+                changed = true;
+            }
         }
 
-        sleep(Duration::from_secs(5)).await;
+        Ok(if changed { WorkerState::Active } else { WorkerState::Idle })
     }
 }
 
-async fn reconcile(docker: &Docker, backoff: &mut BackoffState) -> Result<(), Error> {
-    let mut filters = HashMap::new();
-    filters.insert(
-        "label".to_string(),
-        vec![format!("service={}", SERVICE_NAME)],
-    );
-
-    let options = ListContainersOptions {
-        all: true,
-        limit: None,
-        size: false,
-        filters: Some(filters),
-    };
+struct GcWorker {
+    docker: Docker,
+    spec_path: PathBuf,
+}
 
-    let containers = docker.list_containers(Some(options)).await?;
+#[async_trait]
+impl Worker for GcWorker {
+    fn name(&self) -> &str {
+        "gc"
+    }
 
-    let mut running = Vec::new();
-    let mut dead = Vec::new();
+    async fn step(&mut self) -> Result<WorkerState, OrchestratorError> {
+        let config = load_spec(&self.spec_path).await?;
+        let known_services = config.services.iter().map(|s| s.name.clone()).collect();
+        let changed = gc_orphaned_containers(&self.docker, &known_services).await?;
+        Ok(if changed { WorkerState::Active } else { WorkerState::Idle })
+    }
+}
 
-    for c in containers {
-        let id = c.id.unwrap_or_default();
-        let state = c.state.unwrap_or(ContainerSummaryStateEnum::EMPTY);
-        let status = c.status.unwrap_or_default().to_lowercase();
+#[tokio::main]
+async fn main() -> Result<(), OrchestratorError> {
+    let spec_path = parse_args();
+    let docker = connect_with_retry().await;
 
-        let is_running = matches!(state, ContainerSummaryStateEnum::RUNNING)
-            || status.contains("up")
-            || status.contains("running");
+    // Load once up front so a broken spec file fails fast instead of only
+    // surfacing as a worker error on the first reconcile cycle.
+    let config = load_spec(&spec_path).await?;
 
-        if is_running {
-            running.push(id);
-        } else {
-            dead.push(id);
-        }
+    println!("Connected to docker, starting orchestrataaa");
+    println!("Spec: {}", spec_path.display());
+    for service in &config.services {
+        println!(
+            "Service: {}, image: {}, desired replicas: {}, depends_on: {:?}",
+            service.name, service.image, service.desired_replicas, service.depends_on
+        );
     }
 
-    println!(
-        "[reconcile] running: {}, dead: {}, backoff: {:?}",
-        running.len(),
-        dead.len(),
-        backoff
-    );
+    let retry_queues: Arc<Mutex<HashMap<String, RetryQueue>>> = Arc::new(Mutex::new(HashMap::new()));
 
-    if !dead.is_empty() {
-        backoff.register_failure();
-    } else {
-        backoff.maybe_reset();
-    }
+    let mut scheduler = Scheduler::new();
 
-    for id in &dead {
-        println!("Removing dead container: {id}");
-        graceful_remove_container(docker, id).await?;
-    }
-
-    let running_count = running.len();
+    scheduler.spawn(
+        Box::new(ReconcilerWorker {
+            docker: docker.clone(),
+            spec_path: spec_path.clone(),
+            retry_queues: retry_queues.clone(),
+        }),
+        Duration::from_secs(RECONCILE_INTERVAL_SECS),
+    );
 
-    let mut outdated = Vec::new();
+    scheduler.spawn(
+        Box::new(HealthWatcherWorker {
+            docker: docker.clone(),
+            spec_path: spec_path.clone(),
+            retry_queues: retry_queues.clone(),
+            unhealthy_since: HashMap::new(),
+        }),
+        Duration::from_secs(HEALTH_WATCH_INTERVAL_SECS),
+    );
 
-    for id in &running {
-        let inspect = docker
-            .inspect_container(id, None::<InspectContainerOptions>)
-            .await?;
+    scheduler.spawn(
+        Box::new(GcWorker { docker: docker.clone(), spec_path: spec_path.clone() }),
+        Duration::from_secs(GC_INTERVAL_SECS),
+    );
 
-        let version = inspect
-            .config
-            .as_ref()
-            .and_then(|cfg| cfg.labels.as_ref())
-            .and_then(|labels| labels.get("version"))
-            .map(|v| v.as_str());
+    let scheduler = Arc::new(scheduler);
 
-        if version != Some(IMAGE) {
-            outdated.push(id.clone());
+    // run_control_loop never returns (it idles forever once stdin closes,
+    // so a non-interactive launch can't race this select into shutting
+    // down); only the signal handler resolves this.
+    tokio::select! {
+        _ = run_control_loop(scheduler.clone()) => {
+            unreachable!("run_control_loop never returns");
+        }
+        _ = wait_for_shutdown_signal() => {
+            println!("Shutdown signal received, tearing down managed containers");
         }
     }
 
-    if !outdated.is_empty() {
-        println!("Found outdated containers, performing rolling update");
-        return perform_rolling_update(docker, &running).await;
-    }
+    let scheduler = Arc::try_unwrap(scheduler)
+        .unwrap_or_else(|_| panic!("scheduler still has outstanding references at shutdown"));
+    scheduler.cancel_all().await;
 
-    if running_count < DESIRED_REPLICAS {
-        let to_spawn = DESIRED_REPLICAS - running_count;
+    shutdown(&docker, &spec_path).await?;
 
-        if backoff.in_backoff() {
-            println!(
-                "Backoff active ({} failures). Skipping respawn this cycle",
-                backoff.consecutive_failures
-            );
-            return Ok(());
-        }
+    Ok(())
+}
 
-        println!("Need {to_spawn} more replicas");
+/// Waits for either SIGINT or SIGTERM, whichever arrives first.
+async fn wait_for_shutdown_signal() {
+    let mut sigterm = signal(SignalKind::terminate()).expect("failed to install SIGTERM handler");
 
-        for _ in 0..to_spawn {
-            spawn_replica_and_get_id(docker).await?;
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {
+            println!("Received SIGINT");
         }
-        return Ok(());
-    }
-
-    if running_count > DESIRED_REPLICAS {
-        let to_kill = running_count - DESIRED_REPLICAS;
-        println!("Removing {to_kill} extra replicas");
-
-        for id in running.iter().take(to_kill) {
-            graceful_remove_container(docker, id).await?;
+        _ = sigterm.recv() => {
+            println!("Received SIGTERM");
         }
-        return Ok(());
     }
-
-    println!("Desired state satisfied.");
-    Ok(())
 }
 
-async fn spawn_replica_and_get_id(docker: &Docker) -> Result<String, Error> {
-    let container_name = format!("{}-{}", SERVICE_NAME, Uuid::new_v4());
+/// Tears down every container this orchestrator manages, honoring an
+/// overall deadline read from the spec's `shutdown_deadline_secs`
+/// (falling back to `DEFAULT_SHUTDOWN_DEADLINE_SECS` if the spec can't be
+/// reloaded at shutdown time). If the graceful removals exceed that
+/// deadline, whatever is still left is force-removed; containers whose
+/// graceful removal failed before the deadline are not force-removed.
+async fn shutdown(docker: &Docker, spec_path: &Path) -> Result<(), OrchestratorError> {
+    let deadline_secs = match load_spec(spec_path).await {
+        Ok(config) => config.shutdown_deadline_secs,
+        Err(e) => {
+            eprintln!("failed to reload spec for shutdown deadline, using default: {e}");
+            DEFAULT_SHUTDOWN_DEADLINE_SECS
+        }
+    };
 
-    let mut labels = HashMap::new();
-    labels.insert("service".to_string(), SERVICE_NAME.to_string());
-    labels.insert("managed-by".to_string(), "bollard-orchestrator".to_string());
-    labels.insert("version".to_string(), IMAGE.to_string());
+    let ids = list_managed_containers(docker).await?;
+    println!("Shutting down: removing {} managed container(s)", ids.len());
 
-    let body = ContainerCreateBody {
-        image: Some(IMAGE.to_string()),
-        labels: Some(labels),
-        host_config: Some(HostConfig {
-            ..Default::default()
-        }),
-        ..Default::default()
+    let graceful = async {
+        for id in &ids {
+            if let Err(e) = reconcile::graceful_remove_container(docker, id).await {
+                eprintln!("failed to gracefully remove {id}: {e}");
+            }
+        }
     };
 
-    let options = CreateContainerOptions {
-        name: Some(container_name.clone()),
-        platform: "".into(),
-    };
+    if tokio::time::timeout(Duration::from_secs(deadline_secs), graceful)
+        .await
+        .is_err()
+    {
+        eprintln!("Shutdown deadline exceeded, force-removing remaining containers");
+        force_remove_remaining(docker).await?;
+    }
 
-    docker.create_container(Some(options), body).await?;
+    Ok(())
+}
 
-    println!("Starting container: {container_name}");
-    docker
-        .start_container(&container_name, Some(StartContainerOptions::default()))
-        .await?;
+/// Reads the spec file path from the first CLI argument, exiting with a
+/// usage message if it's missing.
+fn parse_args() -> PathBuf {
+    let Some(path) = std::env::args().nth(1) else {
+        eprintln!("usage: bollardo <spec.yaml>");
+        std::process::exit(1);
+    };
 
-    Ok(container_name)
+    PathBuf::from(path)
 }
 
 async fn connect_with_retry() -> Docker {
@@ -229,46 +249,3 @@ async fn connect_with_retry() -> Docker {
         }
     }
 }
-
-async fn graceful_remove_container(docker: &Docker, id: &str) -> Result<(), Error> {
-    println!("Gracefully stopping container: {id}");
-
-    let stop_options = StopContainerOptions {
-        signal: None,
-        t: Some(5),
-    };
-
-    let _ = docker.stop_container(id, Some(stop_options)).await;
-
-    println!("Removing container: {id}");
-    docker
-        .remove_container(
-            id,
-            Some(RemoveContainerOptions {
-                force: false,
-                v: false,
-                link: false,
-            }),
-        )
-        .await?;
-
-    Ok(())
-}
-
-async fn perform_rolling_update(docker: &Docker, running: &Vec<String>) -> Result<(), Error> {
-    println!("Starting rolling update for image = {}", IMAGE);
-
-    for old_id in running {
-        println!("Spawning new replica before removing: {old_id}");
-        let new_id = spawn_replica_and_get_id(docker).await?;
-
-        tokio::time::sleep(Duration::from_secs(3)).await;
-
-        graceful_remove_container(docker, old_id).await?;
-
-        println!("Replaced {old_id} with {new_id}");
-    }
-
-    println!("Rolling update done");
-    Ok(())
-}
@@ -0,0 +1,565 @@
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use bollard::Docker;
+use bollard::models::{
+    ContainerCreateBody, ContainerSummaryStateEnum, HealthStatusEnum, HostConfig, PortBinding, PortMap,
+    RestartPolicy, RestartPolicyNameEnum,
+};
+use bollard::query_parameters::{
+    CreateContainerOptions, InspectContainerOptions, ListContainersOptions, RemoveContainerOptions,
+    StartContainerOptions, StopContainerOptions,
+};
+use uuid::Uuid;
+
+use crate::retry::RetryQueue;
+use crate::service::{topo_sort, OrchestratorError, ServiceSpec};
+
+pub const MANAGED_BY_LABEL: &str = "managed-by=bollard-orchestrator";
+
+/// A single container backing a service, along with the replica slot it was
+/// spawned into (read back from its `slot` label).
+pub struct Replica {
+    pub id: String,
+    pub slot: Option<usize>,
+    pub running: bool,
+}
+
+/// Builds the key under which a slot's retry state is tracked.
+fn slot_key(service_name: &str, slot: usize) -> String {
+    format!("{service_name}/{slot}")
+}
+
+/// Reconciles every service in dependency order: a service's replicas are
+/// only spawned once all of its dependencies have reached their desired
+/// replica count. Excess replicas are torn down in reverse dependency order
+/// so dependents are scaled down before the services they depend on.
+/// Returns whether any container was spawned, removed, or replaced.
+pub async fn reconcile_all(
+    docker: &Docker,
+    services: &[ServiceSpec],
+    retry_queues: &mut HashMap<String, RetryQueue>,
+) -> Result<bool, OrchestratorError> {
+    let order = topo_sort(services)?;
+    let by_name: HashMap<&str, &ServiceSpec> =
+        services.iter().map(|s| (s.name.as_str(), s)).collect();
+
+    let mut changed = false;
+
+    for name in &order {
+        let service = by_name[name.as_str()];
+        let deps_ready = dependencies_ready(docker, service, &by_name).await?;
+        let retry = retry_queues
+            .entry(service.name.clone())
+            .or_insert_with(|| RetryQueue::new(service.backoff.clone()));
+
+        if reconcile_up(docker, service, deps_ready, retry).await? {
+            changed = true;
+        }
+    }
+
+    for name in order.iter().rev() {
+        let service = by_name[name.as_str()];
+
+        if reconcile_down(docker, service).await? {
+            changed = true;
+        }
+    }
+
+    Ok(changed)
+}
+
+async fn dependencies_ready(
+    docker: &Docker,
+    service: &ServiceSpec,
+    by_name: &HashMap<&str, &ServiceSpec>,
+) -> Result<bool, OrchestratorError> {
+    for dep_name in &service.depends_on {
+        let Some(dep) = by_name.get(dep_name.as_str()) else {
+            continue;
+        };
+
+        let running = count_running(docker, dep_name).await?;
+
+        if running < dep.desired_replicas {
+            println!(
+                "[reconcile] {} waiting on dependency {} ({running}/{} running)",
+                service.name, dep_name, dep.desired_replicas
+            );
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+async fn count_running(docker: &Docker, service_name: &str) -> Result<usize, OrchestratorError> {
+    let replicas = list_service_containers(docker, service_name).await?;
+    Ok(replicas.iter().filter(|r| r.running).count())
+}
+
+pub async fn list_service_containers(
+    docker: &Docker,
+    service_name: &str,
+) -> Result<Vec<Replica>, OrchestratorError> {
+    let mut filters = HashMap::new();
+    filters.insert("label".to_string(), vec![format!("service={service_name}")]);
+
+    let options = ListContainersOptions {
+        all: true,
+        limit: None,
+        size: false,
+        filters: Some(filters),
+    };
+
+    let containers = docker.list_containers(Some(options)).await?;
+
+    let replicas = containers
+        .into_iter()
+        .map(|c| {
+            let id = c.id.unwrap_or_default();
+            let state = c.state.unwrap_or(ContainerSummaryStateEnum::EMPTY);
+            let status = c.status.unwrap_or_default().to_lowercase();
+
+            let running = matches!(state, ContainerSummaryStateEnum::RUNNING)
+                || status.contains("up")
+                || status.contains("running");
+
+            let slot = c
+                .labels
+                .as_ref()
+                .and_then(|labels| labels.get("slot"))
+                .and_then(|slot| slot.parse().ok());
+
+            Replica { id, slot, running }
+        })
+        .collect();
+
+    Ok(replicas)
+}
+
+/// Removes dead replicas and spawns new ones up to the desired count, or
+/// kicks off a rolling update if the running replicas are outdated. Does not
+/// remove excess replicas; see `reconcile_down`. Returns whether it changed
+/// anything.
+async fn reconcile_up(
+    docker: &Docker,
+    service: &ServiceSpec,
+    deps_ready: bool,
+    retry: &mut RetryQueue,
+) -> Result<bool, OrchestratorError> {
+    let mut replicas = list_service_containers(docker, &service.name).await?;
+    let mut changed = false;
+
+    println!(
+        "[reconcile] service: {}, running: {}, dead: {}, retry: {}",
+        service.name,
+        replicas.iter().filter(|r| r.running).count(),
+        replicas.iter().filter(|r| !r.running).count(),
+        retry.describe(),
+    );
+
+    for replica in replicas.iter().filter(|r| !r.running) {
+        println!("Removing dead container: {}", replica.id);
+        graceful_remove_container(docker, &replica.id).await?;
+        changed = true;
+
+        if let Some(slot) = replica.slot {
+            retry.register_failure(&slot_key(&service.name, slot), "replica exited");
+        }
+    }
+
+    replicas.retain(|r| r.running);
+
+    let mut outdated = Vec::new();
+
+    for replica in &replicas {
+        let inspect = docker
+            .inspect_container(&replica.id, None::<InspectContainerOptions>)
+            .await?;
+
+        let version = inspect
+            .config
+            .as_ref()
+            .and_then(|cfg| cfg.labels.as_ref())
+            .and_then(|labels| labels.get("version"))
+            .map(|v| v.as_str());
+
+        if version != Some(service.image.as_str()) {
+            outdated.push(replica.id.clone());
+        }
+    }
+
+    if !outdated.is_empty() {
+        println!("Found outdated containers, performing rolling update");
+        perform_rolling_update(docker, service, &replicas).await?;
+        return Ok(true);
+    }
+
+    if !deps_ready {
+        return Ok(changed);
+    }
+
+    let occupied: HashSet<usize> = replicas.iter().filter_map(|r| r.slot).collect();
+    let missing_slots: Vec<usize> = (0..service.desired_replicas)
+        .filter(|slot| !occupied.contains(slot))
+        .collect();
+
+    if missing_slots.is_empty() {
+        return Ok(changed);
+    }
+
+    println!("Need {} more replicas for {}", missing_slots.len(), service.name);
+
+    for slot in missing_slots {
+        let key = slot_key(&service.name, slot);
+
+        if !retry.ready(&key) {
+            println!("Slot {slot} of {} is in backoff, skipping this cycle", service.name);
+            continue;
+        }
+
+        match spawn_replica_and_get_id(docker, service, slot).await {
+            Ok(id) => {
+                println!("Spawned {id} for slot {slot}");
+                changed = true;
+            }
+            Err(e) => {
+                eprintln!("Failed to spawn replica for slot {slot} of {}: {e}", service.name);
+                retry.register_failure(&key, e);
+            }
+        }
+    }
+
+    Ok(changed)
+}
+
+/// Removes replicas in excess of the desired count for `service`. Returns
+/// whether anything was removed.
+async fn reconcile_down(docker: &Docker, service: &ServiceSpec) -> Result<bool, OrchestratorError> {
+    let replicas = list_service_containers(docker, &service.name).await?;
+    let running: Vec<String> = replicas
+        .into_iter()
+        .filter(|r| r.running)
+        .map(|r| r.id)
+        .collect();
+
+    if running.len() <= service.desired_replicas {
+        return Ok(false);
+    }
+
+    let to_kill = running.len() - service.desired_replicas;
+    println!("Removing {to_kill} extra replicas for {}", service.name);
+
+    for id in running.iter().take(to_kill) {
+        graceful_remove_container(docker, id).await?;
+    }
+
+    Ok(true)
+}
+
+async fn list_unhealthy_containers(
+    docker: &Docker,
+    service_name: &str,
+) -> Result<HashSet<String>, OrchestratorError> {
+    let mut filters = HashMap::new();
+    filters.insert("label".to_string(), vec![format!("service={service_name}")]);
+    filters.insert("health".to_string(), vec!["unhealthy".to_string()]);
+
+    let options = ListContainersOptions {
+        all: true,
+        limit: None,
+        size: false,
+        filters: Some(filters),
+    };
+
+    let containers = docker.list_containers(Some(options)).await?;
+
+    Ok(containers.into_iter().map(|c| c.id.unwrap_or_default()).collect())
+}
+
+/// Watches HEALTHCHECK-reported status for `service`'s running replicas,
+/// removing any that have stayed unhealthy continuously for longer than
+/// `service.unhealthy_timeout_secs`. Runs separately from
+/// `reconcile_up`/`_down`; both take the same retry queue, so while a health
+/// check holds it a reconcile pass waits. Returns whether it removed anything.
+pub async fn health_check_service(
+    docker: &Docker,
+    service: &ServiceSpec,
+    retry: &mut RetryQueue,
+    unhealthy_since: &mut HashMap<String, Instant>,
+) -> Result<bool, OrchestratorError> {
+    let replicas = list_service_containers(docker, &service.name).await?;
+    let unhealthy_candidates = list_unhealthy_containers(docker, &service.name).await?;
+    let mut removed_any = false;
+
+    // A container can disappear from here entirely — removed by
+    // reconcile_up's dead-container cleanup, reconcile_down scaling down, or
+    // perform_rolling_update — without this function ever seeing it healthy
+    // or hitting the timeout itself. Prune those stale entries so
+    // unhealthy_since doesn't grow unbounded over the orchestrator's
+    // lifetime; container names are UUID-suffixed, so ids never recur.
+    let current_ids: HashSet<&str> = replicas.iter().map(|r| r.id.as_str()).collect();
+    unhealthy_since.retain(|id, _| current_ids.contains(id.as_str()));
+
+    for replica in replicas.iter().filter(|r| r.running) {
+        if !unhealthy_candidates.contains(&replica.id) {
+            unhealthy_since.remove(&replica.id);
+
+            if let Some(slot) = replica.slot {
+                retry.note_healthy(&slot_key(&service.name, slot));
+            }
+            continue;
+        }
+
+        let inspect = docker
+            .inspect_container(&replica.id, None::<InspectContainerOptions>)
+            .await?;
+
+        let health_status = inspect
+            .state
+            .as_ref()
+            .and_then(|state| state.health.as_ref())
+            .and_then(|health| health.status);
+
+        if health_status != Some(HealthStatusEnum::UNHEALTHY) {
+            unhealthy_since.remove(&replica.id);
+            continue;
+        }
+
+        let since = *unhealthy_since
+            .entry(replica.id.clone())
+            .or_insert_with(Instant::now);
+        let elapsed = since.elapsed();
+
+        println!("[health-watcher] {} unhealthy for {}s", replica.id, elapsed.as_secs());
+
+        if elapsed > Duration::from_secs(service.unhealthy_timeout_secs) {
+            println!(
+                "[health-watcher] {} unhealthy for over {}s, removing",
+                replica.id, service.unhealthy_timeout_secs
+            );
+            graceful_remove_container(docker, &replica.id).await?;
+            unhealthy_since.remove(&replica.id);
+            removed_any = true;
+
+            if let Some(slot) = replica.slot {
+                retry.register_failure(&slot_key(&service.name, slot), "unhealthy timeout exceeded");
+            }
+        }
+    }
+
+    Ok(removed_any)
+}
+
+/// Removes containers carrying the `managed-by=bollard-orchestrator` label
+/// whose `service` label no longer matches any currently configured
+/// service — leftovers from a service that was removed from the spec while
+/// the orchestrator was running. Returns whether anything was removed.
+pub async fn gc_orphaned_containers(
+    docker: &Docker,
+    known_services: &HashSet<String>,
+) -> Result<bool, OrchestratorError> {
+    let mut filters = HashMap::new();
+    filters.insert("label".to_string(), vec![MANAGED_BY_LABEL.to_string()]);
+
+    let options = ListContainersOptions {
+        all: true,
+        limit: None,
+        size: false,
+        filters: Some(filters),
+    };
+
+    let containers = docker.list_containers(Some(options)).await?;
+    let mut removed_any = false;
+
+    for c in containers {
+        let id = c.id.unwrap_or_default();
+        let service_name = c.labels.as_ref().and_then(|labels| labels.get("service").cloned());
+
+        let orphaned = match &service_name {
+            Some(name) => !known_services.contains(name),
+            None => true,
+        };
+
+        if orphaned {
+            println!("[gc] removing orphaned container {id} (service={service_name:?})");
+            graceful_remove_container(docker, &id).await?;
+            removed_any = true;
+        }
+    }
+
+    Ok(removed_any)
+}
+
+pub async fn list_managed_containers(docker: &Docker) -> Result<Vec<String>, OrchestratorError> {
+    let mut filters = HashMap::new();
+    filters.insert("label".to_string(), vec![MANAGED_BY_LABEL.to_string()]);
+
+    let options = ListContainersOptions {
+        all: true,
+        limit: None,
+        size: false,
+        filters: Some(filters),
+    };
+
+    let containers = docker.list_containers(Some(options)).await?;
+    Ok(containers.into_iter().map(|c| c.id.unwrap_or_default()).collect())
+}
+
+pub async fn force_remove_remaining(docker: &Docker) -> Result<(), OrchestratorError> {
+    for id in list_managed_containers(docker).await? {
+        println!("Force-removing container: {id}");
+        docker
+            .remove_container(
+                &id,
+                Some(RemoveContainerOptions {
+                    force: true,
+                    v: false,
+                    link: false,
+                }),
+            )
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Maps a spec's restart policy name to Docker's restart policy enum,
+/// falling back to the daemon default (no restart policy) for anything
+/// unrecognized.
+fn restart_policy_name(name: &str) -> RestartPolicyNameEnum {
+    match name {
+        "no" => RestartPolicyNameEnum::NO,
+        "always" => RestartPolicyNameEnum::ALWAYS,
+        "unless-stopped" => RestartPolicyNameEnum::UNLESS_STOPPED,
+        "on-failure" => RestartPolicyNameEnum::ON_FAILURE,
+        _ => RestartPolicyNameEnum::EMPTY,
+    }
+}
+
+async fn spawn_replica_and_get_id(
+    docker: &Docker,
+    service: &ServiceSpec,
+    slot: usize,
+) -> Result<String, OrchestratorError> {
+    let container_name = format!("{}-{}-{}", service.name, slot, Uuid::new_v4());
+
+    let mut labels = service.labels.clone();
+    labels.insert("service".to_string(), service.name.clone());
+    labels.insert("managed-by".to_string(), "bollard-orchestrator".to_string());
+    labels.insert("version".to_string(), service.image.clone());
+    labels.insert("slot".to_string(), slot.to_string());
+
+    let env = if service.env.is_empty() { None } else { Some(service.env.clone()) };
+
+    let exposed_ports = if service.ports.is_empty() {
+        None
+    } else {
+        Some(
+            service
+                .ports
+                .iter()
+                .map(|p| format!("{}/{}", p.container_port, p.protocol))
+                .collect(),
+        )
+    };
+
+    let port_bindings: Option<PortMap> = if service.ports.is_empty() {
+        None
+    } else {
+        let mut bindings: PortMap = HashMap::new();
+
+        for port in &service.ports {
+            bindings.insert(
+                format!("{}/{}", port.container_port, port.protocol),
+                Some(vec![PortBinding {
+                    host_ip: None,
+                    host_port: Some(port.host_port.to_string()),
+                }]),
+            );
+        }
+
+        Some(bindings)
+    };
+
+    let restart_policy = service.restart_policy.as_ref().map(|policy| RestartPolicy {
+        name: Some(restart_policy_name(&policy.name)),
+        maximum_retry_count: policy.maximum_retry_count,
+    });
+
+    let body = ContainerCreateBody {
+        image: Some(service.image.clone()),
+        labels: Some(labels),
+        env,
+        exposed_ports,
+        host_config: Some(HostConfig {
+            port_bindings,
+            restart_policy,
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+
+    let options = CreateContainerOptions {
+        name: Some(container_name.clone()),
+        platform: "".into(),
+    };
+
+    docker.create_container(Some(options), body).await?;
+
+    println!("Starting container: {container_name}");
+    docker
+        .start_container(&container_name, Some(StartContainerOptions::default()))
+        .await?;
+
+    Ok(container_name)
+}
+
+pub async fn graceful_remove_container(docker: &Docker, id: &str) -> Result<(), OrchestratorError> {
+    println!("Gracefully stopping container: {id}");
+
+    let stop_options = StopContainerOptions {
+        signal: None,
+        t: Some(5),
+    };
+
+    let _ = docker.stop_container(id, Some(stop_options)).await;
+
+    println!("Removing container: {id}");
+    docker
+        .remove_container(
+            id,
+            Some(RemoveContainerOptions {
+                force: false,
+                v: false,
+                link: false,
+            }),
+        )
+        .await?;
+
+    Ok(())
+}
+
+async fn perform_rolling_update(
+    docker: &Docker,
+    service: &ServiceSpec,
+    running: &[Replica],
+) -> Result<(), OrchestratorError> {
+    println!("Starting rolling update for service = {}, image = {}", service.name, service.image);
+
+    for old in running {
+        let slot = old.slot.unwrap_or(0);
+        println!("Spawning new replica before removing: {}", old.id);
+        let new_id = spawn_replica_and_get_id(docker, service, slot).await?;
+
+        tokio::time::sleep(Duration::from_secs(3)).await;
+
+        graceful_remove_container(docker, &old.id).await?;
+
+        println!("Replaced {} with {new_id}", old.id);
+    }
+
+    println!("Rolling update done");
+    Ok(())
+}
@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+const MAX_JITTER_MILLIS: u64 = 2_000;
+
+/// Per-service tuning for how aggressively a crash-looping replica backs off.
+#[derive(Debug, Clone)]
+pub struct BackoffConfig {
+    pub base_delay_secs: u64,
+    pub max_delay_secs: u64,
+    pub reset_after_healthy_secs: u64,
+}
+
+impl Default for BackoffConfig {
+    fn default() -> Self {
+        Self {
+            base_delay_secs: 30,
+            max_delay_secs: 300,
+            reset_after_healthy_secs: 300,
+        }
+    }
+}
+
+/// Backoff bookkeeping for a single replica slot.
+#[derive(Debug)]
+struct RetryEntry {
+    attempts: u32,
+    next_attempt: Instant,
+    last_error: String,
+    healthy_since: Option<Instant>,
+}
+
+impl RetryEntry {
+    fn delay_for(attempts: u32, config: &BackoffConfig) -> Duration {
+        let exponent = attempts.saturating_sub(1).min(10);
+        let base_secs = config.base_delay_secs.saturating_mul(1u64 << exponent);
+        let capped_secs = base_secs.min(config.max_delay_secs);
+        let jitter = rand::random_range(0..=MAX_JITTER_MILLIS);
+
+        Duration::from_secs(capped_secs) + Duration::from_millis(jitter)
+    }
+}
+
+/// A keyed retry queue: each slot (e.g. `"{service}/{slot}"`) backs off
+/// independently, so one crash-looping replica no longer poisons respawns
+/// for every other replica of the same service.
+#[derive(Debug)]
+pub struct RetryQueue {
+    config: BackoffConfig,
+    entries: HashMap<String, RetryEntry>,
+}
+
+impl RetryQueue {
+    pub fn new(config: BackoffConfig) -> Self {
+        Self { config, entries: HashMap::new() }
+    }
+
+    /// Whether `key` is allowed to be retried right now.
+    pub fn ready(&self, key: &str) -> bool {
+        self.entries
+            .get(key)
+            .map(|entry| Instant::now() >= entry.next_attempt)
+            .unwrap_or(true)
+    }
+
+    /// Records a failure for `key`, pushing its next retry out by an
+    /// exponentially increasing, jittered delay.
+    pub fn register_failure(&mut self, key: &str, error: impl std::fmt::Display) {
+        let config = &self.config;
+        let entry = self.entries.entry(key.to_string()).or_insert_with(|| RetryEntry {
+            attempts: 0,
+            next_attempt: Instant::now(),
+            last_error: String::new(),
+            healthy_since: None,
+        });
+
+        entry.attempts += 1;
+        entry.next_attempt = Instant::now() + RetryEntry::delay_for(entry.attempts, config);
+        entry.last_error = error.to_string();
+        entry.healthy_since = None;
+    }
+
+    /// Records that `key` is currently healthy. Once it has stayed healthy
+    /// continuously past the reset window, its attempt count is cleared.
+    pub fn note_healthy(&mut self, key: &str) {
+        let reset_after = Duration::from_secs(self.config.reset_after_healthy_secs);
+        let Some(entry) = self.entries.get_mut(key) else {
+            return;
+        };
+
+        let healthy_since = *entry.healthy_since.get_or_insert_with(Instant::now);
+
+        if healthy_since.elapsed() > reset_after {
+            self.entries.remove(key);
+        }
+    }
+
+    /// One-line summary of every slot with retry state (including slots
+    /// whose retry time has already passed), for the `[reconcile]` log line.
+    pub fn describe(&self) -> String {
+        if self.entries.is_empty() {
+            return "none".to_string();
+        }
+
+        let mut parts: Vec<String> = self
+            .entries
+            .iter()
+            .map(|(key, entry)| {
+                let retry_in = entry
+                    .next_attempt
+                    .saturating_duration_since(Instant::now())
+                    .as_secs();
+                format!(
+                    "{key}(attempts={}, retry_in={retry_in}s, last_error={})",
+                    entry.attempts, entry.last_error
+                )
+            })
+            .collect();
+        parts.sort();
+
+        parts.join(", ")
+    }
+}
@@ -0,0 +1,121 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+
+use crate::retry::BackoffConfig;
+
+/// A single `containerPort:hostPort` mapping to publish for a service.
+#[derive(Debug, Clone)]
+pub struct PortBindingSpec {
+    pub host_port: u16,
+    pub container_port: u16,
+    pub protocol: String,
+}
+
+/// Docker restart policy to apply to a service's containers.
+#[derive(Debug, Clone)]
+pub struct RestartPolicySpec {
+    pub name: String,
+    pub maximum_retry_count: Option<i64>,
+}
+
+/// Desired state for a single service the orchestrator manages.
+#[derive(Debug, Clone)]
+pub struct ServiceSpec {
+    pub name: String,
+    pub image: String,
+    pub desired_replicas: usize,
+    pub labels: HashMap<String, String>,
+    pub depends_on: Vec<String>,
+    pub env: Vec<String>,
+    pub ports: Vec<PortBindingSpec>,
+    pub restart_policy: Option<RestartPolicySpec>,
+    pub unhealthy_timeout_secs: u64,
+    pub backoff: BackoffConfig,
+}
+
+/// Errors that can occur while loading the spec or planning or executing a
+/// reconciliation pass, including errors surfaced by the Docker API itself.
+#[derive(Debug)]
+pub enum OrchestratorError {
+    Docker(bollard::errors::Error),
+    DependencyCycle(Vec<String>),
+    Spec(String),
+}
+
+impl fmt::Display for OrchestratorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OrchestratorError::Docker(e) => write!(f, "docker error: {e}"),
+            OrchestratorError::DependencyCycle(services) => {
+                write!(f, "dependency cycle among services: {}", services.join(", "))
+            }
+            OrchestratorError::Spec(msg) => write!(f, "spec error: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for OrchestratorError {}
+
+impl From<bollard::errors::Error> for OrchestratorError {
+    fn from(e: bollard::errors::Error) -> Self {
+        OrchestratorError::Docker(e)
+    }
+}
+
+/// Computes a deterministic start order for `services` via Kahn's algorithm
+/// over the `depends_on` graph. Returns an error naming the services still
+/// stuck in the graph if a dependency cycle is found.
+pub fn topo_sort(services: &[ServiceSpec]) -> Result<Vec<String>, OrchestratorError> {
+    let mut in_degree: HashMap<&str, usize> =
+        services.iter().map(|s| (s.name.as_str(), 0)).collect();
+    let mut dependents: HashMap<&str, Vec<&str>> = HashMap::new();
+
+    for service in services {
+        for dep in &service.depends_on {
+            if !in_degree.contains_key(dep.as_str()) {
+                continue;
+            }
+
+            *in_degree.entry(service.name.as_str()).or_insert(0) += 1;
+            dependents
+                .entry(dep.as_str())
+                .or_default()
+                .push(service.name.as_str());
+        }
+    }
+
+    let mut queue: VecDeque<&str> = services
+        .iter()
+        .map(|s| s.name.as_str())
+        .filter(|name| in_degree[name] == 0)
+        .collect();
+
+    let mut order = Vec::with_capacity(services.len());
+
+    while let Some(name) = queue.pop_front() {
+        order.push(name.to_string());
+
+        if let Some(deps) = dependents.get(name) {
+            for &dependent in deps {
+                let degree = in_degree.get_mut(dependent).expect("known service");
+                *degree -= 1;
+
+                if *degree == 0 {
+                    queue.push_back(dependent);
+                }
+            }
+        }
+    }
+
+    if order.len() != services.len() {
+        let remaining: Vec<String> = services
+            .iter()
+            .map(|s| s.name.clone())
+            .filter(|name| !order.contains(name))
+            .collect();
+
+        return Err(OrchestratorError::DependencyCycle(remaining));
+    }
+
+    Ok(order)
+}
@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::retry::BackoffConfig;
+use crate::service::{OrchestratorError, PortBindingSpec, RestartPolicySpec, ServiceSpec};
+
+/// Fallback used if the spec can't be (re)loaded when shutdown is
+/// triggered, or doesn't set `shutdown_deadline_secs` explicitly.
+pub const DEFAULT_SHUTDOWN_DEADLINE_SECS: u64 = 15;
+
+/// Global (not per-service) desired state plus the services themselves.
+pub struct OrchestratorConfig {
+    pub shutdown_deadline_secs: u64,
+    pub services: Vec<ServiceSpec>,
+}
+
+fn default_shutdown_deadline_secs() -> u64 {
+    DEFAULT_SHUTDOWN_DEADLINE_SECS
+}
+
+fn default_replicas() -> usize {
+    1
+}
+
+fn default_unhealthy_timeout_secs() -> u64 {
+    35
+}
+
+fn default_protocol() -> String {
+    "tcp".to_string()
+}
+
+/// YAML-facing shape of a single `ports` entry.
+#[derive(Debug, Deserialize)]
+struct PortConfig {
+    host_port: u16,
+    container_port: u16,
+    #[serde(default = "default_protocol")]
+    protocol: String,
+}
+
+/// YAML-facing shape of a service's `restart_policy`.
+#[derive(Debug, Deserialize)]
+struct RestartPolicyConfig {
+    name: String,
+    maximum_retry_count: Option<i64>,
+}
+
+/// YAML-facing shape of a service's `backoff` tuning. Any field left unset
+/// falls back to `BackoffConfig::default()`.
+#[derive(Debug, Deserialize, Default)]
+struct BackoffConfigFile {
+    base_delay_secs: Option<u64>,
+    max_delay_secs: Option<u64>,
+    reset_after_healthy_secs: Option<u64>,
+}
+
+/// YAML-facing shape of a single service entry.
+#[derive(Debug, Deserialize)]
+struct ServiceConfig {
+    name: String,
+    image: String,
+    #[serde(default = "default_replicas")]
+    desired_replicas: usize,
+    #[serde(default)]
+    labels: HashMap<String, String>,
+    #[serde(default)]
+    depends_on: Vec<String>,
+    #[serde(default)]
+    env: Vec<String>,
+    #[serde(default)]
+    ports: Vec<PortConfig>,
+    #[serde(default)]
+    restart_policy: Option<RestartPolicyConfig>,
+    #[serde(default = "default_unhealthy_timeout_secs")]
+    unhealthy_timeout_secs: u64,
+    #[serde(default)]
+    backoff: BackoffConfigFile,
+}
+
+impl ServiceConfig {
+    fn into_service_spec(self) -> ServiceSpec {
+        let default_backoff = BackoffConfig::default();
+
+        ServiceSpec {
+            name: self.name,
+            image: self.image,
+            desired_replicas: self.desired_replicas,
+            labels: self.labels,
+            depends_on: self.depends_on,
+            env: self.env,
+            ports: self
+                .ports
+                .into_iter()
+                .map(|p| PortBindingSpec {
+                    host_port: p.host_port,
+                    container_port: p.container_port,
+                    protocol: p.protocol,
+                })
+                .collect(),
+            restart_policy: self.restart_policy.map(|r| RestartPolicySpec {
+                name: r.name,
+                maximum_retry_count: r.maximum_retry_count,
+            }),
+            unhealthy_timeout_secs: self.unhealthy_timeout_secs,
+            backoff: BackoffConfig {
+                base_delay_secs: self.backoff.base_delay_secs.unwrap_or(default_backoff.base_delay_secs),
+                max_delay_secs: self.backoff.max_delay_secs.unwrap_or(default_backoff.max_delay_secs),
+                reset_after_healthy_secs: self
+                    .backoff
+                    .reset_after_healthy_secs
+                    .unwrap_or(default_backoff.reset_after_healthy_secs),
+            },
+        }
+    }
+}
+
+/// YAML-facing shape of the whole spec file: global settings plus a flat
+/// list of services.
+#[derive(Debug, Deserialize)]
+struct Spec {
+    #[serde(default = "default_shutdown_deadline_secs")]
+    shutdown_deadline_secs: u64,
+    services: Vec<ServiceConfig>,
+}
+
+/// Loads and parses the declarative desired-state spec at `path`, translating
+/// it into the `OrchestratorConfig` that drives `reconcile` and shutdown.
+/// Called once at startup and again on every reconcile cycle, so edits to
+/// the file (replica counts, image tags, dependencies, shutdown deadline)
+/// take effect without restarting the orchestrator.
+pub async fn load_spec(path: &Path) -> Result<OrchestratorConfig, OrchestratorError> {
+    let contents = tokio::fs::read_to_string(path)
+        .await
+        .map_err(|e| OrchestratorError::Spec(format!("reading {}: {e}", path.display())))?;
+
+    let spec: Spec = serde_yaml::from_str(&contents)
+        .map_err(|e| OrchestratorError::Spec(format!("parsing {}: {e}", path.display())))?;
+
+    Ok(OrchestratorConfig {
+        shutdown_deadline_secs: spec.shutdown_deadline_secs,
+        services: spec.services.into_iter().map(ServiceConfig::into_service_spec).collect(),
+    })
+}
@@ -0,0 +1,247 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::sync::{mpsc, Mutex};
+use tokio::task::JoinHandle;
+
+use crate::service::OrchestratorError;
+
+/// What a worker reports after a single `step`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorkerState {
+    /// The step changed something (spawned/removed a container, etc).
+    Active,
+    /// The step ran but found nothing to do.
+    Idle,
+    /// The worker is finished and should not be polled again.
+    Done,
+}
+
+/// A unit of long-running orchestrator work, driven by the `Scheduler`.
+#[async_trait]
+pub trait Worker: Send {
+    fn name(&self) -> &str;
+    async fn step(&mut self) -> Result<WorkerState, OrchestratorError>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RunState {
+    Running,
+    Paused,
+}
+
+enum Command {
+    Pause,
+    Resume,
+    Cancel,
+}
+
+/// Point-in-time status of a single worker, as reported by `Scheduler::list`.
+#[derive(Debug, Clone)]
+pub struct WorkerStatus {
+    pub name: String,
+    pub run_state: &'static str,
+    pub last_state: WorkerState,
+    pub last_error: Option<String>,
+    pub iterations: u64,
+    pub time_in_state: Duration,
+}
+
+struct SharedStatus {
+    run_state: RunState,
+    last_state: WorkerState,
+    last_error: Option<String>,
+    iterations: u64,
+    since: Instant,
+}
+
+struct WorkerHandle {
+    name: String,
+    commands: mpsc::Sender<Command>,
+    status: Arc<Mutex<SharedStatus>>,
+    join: JoinHandle<()>,
+}
+
+/// Runs a fixed set of `Worker`s as independent tokio tasks and exposes
+/// operator control over each one (pause/resume/cancel) plus a live status
+/// snapshot, without needing to stop the whole process.
+#[derive(Default)]
+pub struct Scheduler {
+    workers: Vec<WorkerHandle>,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Spawns `worker`, polling it every `interval` until paused or cancelled.
+    pub fn spawn(&mut self, mut worker: Box<dyn Worker>, interval: Duration) {
+        let name = worker.name().to_string();
+        let (tx, mut rx) = mpsc::channel(8);
+        let status = Arc::new(Mutex::new(SharedStatus {
+            run_state: RunState::Running,
+            last_state: WorkerState::Idle,
+            last_error: None,
+            iterations: 0,
+            since: Instant::now(),
+        }));
+
+        let status_for_task = status.clone();
+        let join = tokio::spawn(async move {
+            let mut paused = false;
+
+            loop {
+                while let Ok(cmd) = rx.try_recv() {
+                    match cmd {
+                        Command::Pause => paused = true,
+                        Command::Resume => paused = false,
+                        Command::Cancel => return,
+                    }
+                }
+
+                {
+                    let mut guard = status_for_task.lock().await;
+                    guard.run_state = if paused { RunState::Paused } else { RunState::Running };
+                }
+
+                if paused {
+                    tokio::time::sleep(Duration::from_millis(200)).await;
+                    continue;
+                }
+
+                let outcome = worker.step().await;
+                let mut guard = status_for_task.lock().await;
+                guard.iterations += 1;
+
+                let done = match outcome {
+                    Ok(state) => {
+                        if guard.last_state != state {
+                            guard.since = Instant::now();
+                        }
+                        guard.last_state = state;
+                        guard.last_error = None;
+                        state == WorkerState::Done
+                    }
+                    Err(e) => {
+                        guard.last_error = Some(e.to_string());
+                        false
+                    }
+                };
+                drop(guard);
+
+                if done {
+                    return;
+                }
+
+                tokio::time::sleep(interval).await;
+            }
+        });
+
+        self.workers.push(WorkerHandle { name, commands: tx, status, join });
+    }
+
+    pub async fn list(&self) -> Vec<WorkerStatus> {
+        let mut statuses = Vec::with_capacity(self.workers.len());
+
+        for handle in &self.workers {
+            let guard = handle.status.lock().await;
+            statuses.push(WorkerStatus {
+                name: handle.name.clone(),
+                run_state: match guard.run_state {
+                    RunState::Running => "running",
+                    RunState::Paused => "paused",
+                },
+                last_state: guard.last_state,
+                last_error: guard.last_error.clone(),
+                iterations: guard.iterations,
+                time_in_state: guard.since.elapsed(),
+            });
+        }
+
+        statuses
+    }
+
+    pub async fn pause(&self, name: &str) -> bool {
+        self.send(name, Command::Pause).await
+    }
+
+    pub async fn resume(&self, name: &str) -> bool {
+        self.send(name, Command::Resume).await
+    }
+
+    pub async fn cancel(&self, name: &str) -> bool {
+        self.send(name, Command::Cancel).await
+    }
+
+    /// Cancels every worker and waits for their tasks to finish.
+    pub async fn cancel_all(self) {
+        for handle in &self.workers {
+            let _ = handle.commands.send(Command::Cancel).await;
+        }
+
+        for handle in self.workers {
+            let _ = handle.join.await;
+        }
+    }
+
+    async fn send(&self, name: &str, command: Command) -> bool {
+        let Some(handle) = self.workers.iter().find(|h| h.name == name) else {
+            return false;
+        };
+
+        handle.commands.send(command).await.is_ok()
+    }
+}
+
+/// Reads operator commands from stdin (`list`, `pause <name>`,
+/// `resume <name>`, `cancel <name>`). Under a non-interactive launch
+/// (`docker run -d`, a systemd unit, stdin redirected to `/dev/null`) stdin
+/// reads EOF immediately; rather than returning and winning the shutdown
+/// race in `main`, this idles forever once stdin closes so the orchestrator
+/// stays up and reconciling until an explicit signal tells it to stop.
+pub async fn run_control_loop(scheduler: Arc<Scheduler>) {
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+
+    loop {
+        let line = match lines.next_line().await {
+            Ok(Some(line)) => line,
+            Ok(None) | Err(_) => {
+                std::future::pending::<()>().await;
+                unreachable!("pending future never resolves");
+            }
+        };
+
+        let mut parts = line.split_whitespace();
+        let Some(cmd) = parts.next() else { continue };
+        let arg = parts.next();
+
+        match (cmd, arg) {
+            ("list", _) => {
+                for status in scheduler.list().await {
+                    println!(
+                        "{}: {} ({:?}, iterations={}, time_in_state={}s, last_error={:?})",
+                        status.name,
+                        status.run_state,
+                        status.last_state,
+                        status.iterations,
+                        status.time_in_state.as_secs(),
+                        status.last_error
+                    );
+                }
+            }
+            ("pause", Some(name)) => {
+                println!("pause {name}: {}", scheduler.pause(name).await);
+            }
+            ("resume", Some(name)) => {
+                println!("resume {name}: {}", scheduler.resume(name).await);
+            }
+            ("cancel", Some(name)) => {
+                println!("cancel {name}: {}", scheduler.cancel(name).await);
+            }
+            _ => println!("unrecognized command: {line}"),
+        }
+    }
+}